@@ -1,40 +1,227 @@
-//! A pool that uses the power-of-two-choices algorithm to select endpoints.
+//! A pool that uses the power-of-k-choices algorithm to select endpoints.
 //!
 // Based on tower::p2c::Balance. Copyright (c) 2019 Tower Contributors
 
 use super::{Pool, Update};
 use ahash::AHashMap;
-use futures_util::TryFutureExt;
+use futures_util::{ready, TryFuture, TryFutureExt};
 use linkerd_error::Error;
 use linkerd_metrics::prom;
 use linkerd_stack::{NewService, Service};
+use pin_project_lite::pin_project;
 use rand::{rngs::SmallRng, thread_rng, Rng, SeedableRng};
 use std::{
-    collections::hash_map::Entry,
+    collections::{hash_map::Entry, VecDeque},
+    future::Future,
     net::SocketAddr,
+    pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
+use tokio::{sync::mpsc, time::Instant};
 use tower::{
     load::Load,
     ready_cache::{error::Failed, ReadyCache},
 };
 
 /// Dispatches requests to a pool of services selected by the
-/// power-of-two-choices algorithm.
-#[derive(Debug)]
+/// power-of-k-choices algorithm.
 pub struct P2cPool<T, N, Req, S> {
     new_endpoint: N,
     endpoints: AHashMap<SocketAddr, T>,
-    pool: ReadyCache<SocketAddr, S, Req>,
+    /// Keyed by `(address, connection id)` so that a single address may back
+    /// more than one independent connection; see [`ConnectionScalingConfig`].
+    pool: ReadyCache<(SocketAddr, usize), S, Req>,
     rng: SmallRng,
     metrics: P2cMetrics,
     next_idx: Option<usize>,
+
+    /// Armed against the nearest upcoming `ejected_until`/drain `deadline`
+    /// so that an elapsed backoff or drain timeout is acted on even if
+    /// nothing else re-polls this pool; see `arm_next_deadline`.
+    timer: Pin<Box<tokio::time::Sleep>>,
+
+    /// The number of endpoints sampled when selecting a ready endpoint.
+    ///
+    /// Clamped to the number of eligible endpoints at selection time; `k ==
+    /// 2` reproduces the classic power-of-two-choices behavior.
+    k: usize,
+
+    /// Passive outlier-detection state, keyed by endpoint address.
+    outliers: AHashMap<SocketAddr, OutlierState>,
+    outlier_config: OutlierDetectorConfig,
+    /// The number of endpoints currently ejected.
+    ejected: usize,
+    /// Used by in-flight request futures to report their outcome back to the
+    /// pool, keyed by address and connection id, so that outlier state can be
+    /// updated and `in_flight` decremented.
+    outlier_tx: mpsc::UnboundedSender<(SocketAddr, usize, bool)>,
+    outlier_rx: mpsc::UnboundedReceiver<(SocketAddr, usize, bool)>,
+
+    /// Per-address connection fan-out state, keyed by endpoint address.
+    conns: AHashMap<SocketAddr, ConnGroup>,
+    scaling: ConnectionScalingConfig,
+
+    /// The number of in-flight requests dispatched to each open connection.
+    ///
+    /// Consulted when a connection is removed so that its eviction can be
+    /// deferred until it has no outstanding requests; see `draining`.
+    in_flight: AHashMap<(SocketAddr, usize), usize>,
+
+    /// Connections that service discovery (or connection scaling) has
+    /// removed but that are still carrying in-flight requests.
+    ///
+    /// A draining connection is excluded from `p2c_ready_index` but
+    /// continues to be driven by `poll_pending` until its in-flight count
+    /// reaches zero or `drain_timeout` elapses, at which point it is
+    /// evicted from `pool`.
+    draining: AHashMap<(SocketAddr, usize), DrainState>,
+    /// How long a draining connection may continue carrying in-flight
+    /// requests before it is forcibly evicted.
+    drain_timeout: Duration,
+}
+
+// Prints the pool's state, leaving out the deadline `timer` and the
+// outcome channel (`outlier_tx`/`outlier_rx`).
+impl<T, N, Req, S> std::fmt::Debug for P2cPool<T, N, Req, S>
+where
+    T: std::fmt::Debug,
+    N: std::fmt::Debug,
+    Req: std::fmt::Debug,
+    S: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("P2cPool")
+            .field("new_endpoint", &self.new_endpoint)
+            .field("endpoints", &self.endpoints)
+            .field("pool", &self.pool)
+            .field("rng", &self.rng)
+            .field("metrics", &self.metrics)
+            .field("next_idx", &self.next_idx)
+            .field("k", &self.k)
+            .field("outliers", &self.outliers)
+            .field("outlier_config", &self.outlier_config)
+            .field("ejected", &self.ejected)
+            .field("conns", &self.conns)
+            .field("scaling", &self.scaling)
+            .field("in_flight", &self.in_flight)
+            .field("draining", &self.draining)
+            .field("drain_timeout", &self.drain_timeout)
+            .finish()
+    }
+}
+
+/// Configures the passive outlier-detection behavior applied to each
+/// endpoint in a [`P2cPool`].
+///
+/// This is modeled loosely on Envoy's passive outlier detection: an endpoint
+/// that fails too often (by consecutive failures or by error rate over a
+/// sliding window) is ejected from selection for a backoff period that grows
+/// with repeated ejections.
+#[derive(Clone, Debug)]
+pub struct OutlierDetectorConfig {
+    /// The number of consecutive failures after which an endpoint is ejected.
+    pub consecutive_failures: u32,
+
+    /// The size, in requests, of the sliding window used for the error-rate
+    /// check.
+    pub window_size: usize,
+
+    /// The minimum number of requests that must be recorded in the window
+    /// before the error-rate check is applied.
+    pub min_requests_in_window: u32,
+
+    /// The fraction (0.0-1.0) of requests in the window that must fail for
+    /// the error-rate check to eject an endpoint.
+    pub error_rate_threshold: f64,
+
+    /// The base duration an endpoint is ejected for. Multiplied by the
+    /// endpoint's ejection count (and capped by `max_ejection_time`) to
+    /// compute the actual backoff.
+    pub base_ejection_time: Duration,
+
+    /// The maximum duration an endpoint may be ejected for.
+    pub max_ejection_time: Duration,
+
+    /// The maximum fraction (0.0-1.0) of the pool that may be ejected at
+    /// once.
+    pub max_ejection_percent: f64,
+}
+
+/// Per-endpoint passive outlier-detection state.
+#[derive(Debug, Default)]
+struct OutlierState {
+    consecutive_failures: u32,
+    window: VecDeque<bool>,
+    ejection_count: u32,
+    ejected_until: Option<Instant>,
+}
+
+/// Configures load-driven connection multiplicity for HTTP/2-multiplexed
+/// endpoints.
+///
+/// Since a single HTTP/2 connection to an endpoint can carry many concurrent
+/// requests, one connection per endpoint is usually enough. But a hot
+/// endpoint can still benefit from spreading load over a handful of
+/// independent connections: when an address's ready connections rank above
+/// `high_watermark` (relative to the rest of the pool's load) for `dwell`,
+/// the pool opens another connection to it (up to `max_per_endpoint`); when
+/// they rank below `low_watermark` for `dwell`, the pool closes one (down to
+/// `min_per_endpoint`). Set `max_per_endpoint` equal to `min_per_endpoint`
+/// (the default) to disable scaling and keep exactly one connection per
+/// endpoint.
+#[derive(Clone, Debug)]
+pub struct ConnectionScalingConfig {
+    /// The minimum number of connections maintained per endpoint.
+    pub min_per_endpoint: usize,
+
+    /// The maximum number of connections allowed per endpoint.
+    pub max_per_endpoint: usize,
+
+    /// The load-rank fraction (0.0-1.0), relative to the rest of the ready
+    /// pool, above which an endpoint's connections are considered
+    /// overloaded.
+    pub high_watermark: f64,
+
+    /// The load-rank fraction (0.0-1.0), relative to the rest of the ready
+    /// pool, below which an endpoint's connections are considered
+    /// underloaded.
+    pub low_watermark: f64,
+
+    /// How long an endpoint must stay above `high_watermark` (or below
+    /// `low_watermark`) before a connection is added (or removed).
+    pub dwell: Duration,
+}
+
+/// The state of a connection that is being gracefully drained after removal.
+#[derive(Debug)]
+struct DrainState {
+    /// The time at which the connection is forcibly evicted regardless of
+    /// outstanding load.
+    deadline: Instant,
+}
+
+/// Per-address connection fan-out state.
+#[derive(Debug, Default)]
+struct ConnGroup {
+    /// The connection ids currently open for this address.
+    ids: Vec<usize>,
+    /// The next connection id to allocate for this address.
+    next_id: usize,
+    /// When this address's connections first ranked above `high_watermark`.
+    high_since: Option<Instant>,
+    /// When this address's connections first ranked below `low_watermark`.
+    low_since: Option<Instant>,
 }
 
 #[derive(Clone, Debug)]
 pub struct P2cMetricFamilies<L, U> {
     endpoints: prom::Family<L, prom::Gauge>,
     updates: prom::Family<U, prom::Counter>,
+    ejections: prom::Family<L, prom::Counter>,
+    ejected: prom::Family<L, prom::Gauge>,
+    connections: prom::Family<L, prom::Gauge>,
+    draining: prom::Family<L, prom::Gauge>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -53,6 +240,23 @@ pub struct P2cMetrics {
     /// Measures the number of DoesNotExist updates received from service
     /// discovery.
     updates_dne: prom::Counter,
+
+    /// Measures the total number of times an endpoint has been ejected by
+    /// passive outlier detection.
+    ejections: prom::Counter,
+
+    /// The number of endpoints currently ejected by passive outlier
+    /// detection.
+    ejected: prom::Gauge,
+
+    /// The total number of open connections across all endpoints. Differs
+    /// from `endpoints` (a count of distinct addresses) when load-driven
+    /// connection scaling maintains more than one connection per address.
+    connections: prom::Gauge,
+
+    /// The number of connections that have been removed from the pool but
+    /// are still being gracefully drained of in-flight requests.
+    draining: prom::Gauge,
 }
 
 impl<T, N, Req, S> P2cPool<T, N, Req, S>
@@ -63,15 +267,37 @@ where
     S::Error: Into<Error>,
     S::Metric: std::fmt::Debug,
 {
-    pub fn new(metrics: P2cMetrics, new_endpoint: N) -> Self {
+    pub fn new(
+        metrics: P2cMetrics,
+        new_endpoint: N,
+        outlier_config: OutlierDetectorConfig,
+        k: usize,
+        scaling: ConnectionScalingConfig,
+        drain_timeout: Duration,
+    ) -> Self {
         let rng = SmallRng::from_rng(&mut thread_rng()).expect("RNG must be seeded");
+        let (outlier_tx, outlier_rx) = mpsc::unbounded_channel();
         Self {
             rng,
             metrics,
             new_endpoint,
             next_idx: None,
+            // No deadline is pending yet; `arm_next_deadline` rearms this on
+            // the next poll once there's something to wait for.
+            timer: Box::pin(tokio::time::sleep_until(Instant::now())),
+            k: k.max(2),
             pool: ReadyCache::default(),
             endpoints: Default::default(),
+            outliers: Default::default(),
+            outlier_config,
+            ejected: 0,
+            outlier_tx,
+            outlier_rx,
+            conns: Default::default(),
+            scaling,
+            in_flight: Default::default(),
+            draining: Default::default(),
+            drain_timeout,
         }
     }
 
@@ -90,12 +316,13 @@ where
                 if t.is_none() {
                     tracing::debug!(?addr, "Creating endpoint");
                     self.metrics.endpoints.inc();
+                    self.outliers.entry(addr).or_default();
                 } else {
                     tracing::debug!(?addr, "Updating endpoint");
+                    self.evict_conns(&addr);
                 }
 
-                let svc = self.new_endpoint.new_service((addr, target.clone()));
-                self.pool.push(addr, svc);
+                self.ensure_min_conns(addr, &target);
                 changed = true;
             }
 
@@ -104,8 +331,9 @@ where
 
         for (addr, _) in remaining.drain() {
             tracing::debug!(?addr, "Removing endpoint");
-            self.pool.evict(&addr);
+            self.evict_conns(&addr);
             self.metrics.endpoints.dec();
+            self.forget_outlier(&addr);
             changed = true;
         }
 
@@ -119,22 +347,29 @@ where
     fn add(&mut self, targets: Vec<(SocketAddr, T)>) -> bool {
         let mut changed = false;
         for (addr, target) in targets.into_iter() {
-            match self.endpoints.entry(addr) {
+            let is_new = match self.endpoints.entry(addr) {
                 Entry::Occupied(e) if e.get() == &target => {
                     tracing::debug!(?addr, "Endpoint unchanged");
                     continue;
                 }
                 Entry::Occupied(mut e) => {
                     e.insert(target.clone());
+                    false
                 }
                 Entry::Vacant(e) => {
                     e.insert(target.clone());
-                    self.metrics.endpoints.inc();
+                    true
                 }
+            };
+            if is_new {
+                tracing::debug!(?addr, "Creating endpoint");
+                self.metrics.endpoints.inc();
+                self.outliers.entry(addr).or_default();
+            } else {
+                tracing::debug!(?addr, "Updating endpoint");
+                self.evict_conns(&addr);
             }
-            tracing::debug!(?addr, "Creating endpoint");
-            let svc = self.new_endpoint.new_service((addr, target));
-            self.pool.push(addr, svc);
+            self.ensure_min_conns(addr, &target);
             changed = true;
         }
         changed
@@ -148,8 +383,9 @@ where
         for addr in addrs.into_iter() {
             if self.endpoints.remove(&addr).is_some() {
                 tracing::debug!(?addr, "Removing endpoint");
-                self.pool.evict(&addr);
+                self.evict_conns(&addr);
                 self.metrics.endpoints.dec();
+                self.forget_outlier(&addr);
                 changed = true;
             } else {
                 tracing::debug!(?addr, "Unknown endpoint");
@@ -163,34 +399,444 @@ where
     /// Returns true if the pool was changed.
     fn clear(&mut self) -> bool {
         let changed = !self.endpoints.is_empty();
-        for (addr, _) in self.endpoints.drain() {
+        let addrs: Vec<SocketAddr> = self.endpoints.drain().map(|(addr, _)| addr).collect();
+        for addr in addrs {
             tracing::debug!(?addr, "Clearing endpoint");
-            self.pool.evict(&addr);
             self.metrics.endpoints.dec();
+            self.evict_conns(&addr);
+            self.forget_outlier(&addr);
         }
         changed
     }
 
+    /// Opens a new connection to `addr`, registering it under a fresh
+    /// connection id.
+    fn push_conn(&mut self, addr: SocketAddr, target: T) {
+        let id = {
+            let group = self.conns.entry(addr).or_default();
+            let id = group.next_id;
+            group.next_id += 1;
+            group.ids.push(id);
+            id
+        };
+        tracing::debug!(?addr, id, "Opening connection");
+        let svc = self.new_endpoint.new_service((addr, target));
+        self.pool.push((addr, id), svc);
+        self.metrics.connections.inc();
+    }
+
+    /// Closes the most recently opened connection to `addr`, if any.
+    fn evict_one_conn(&mut self, addr: &SocketAddr) {
+        let id = match self.conns.get_mut(addr) {
+            Some(group) => group.ids.pop(),
+            None => None,
+        };
+        if let Some(id) = id {
+            self.begin_drain(*addr, id);
+        }
+    }
+
+    /// Closes all connections to `addr`.
+    ///
+    /// The fan-out state's `next_id` counter is kept (rather than dropping
+    /// the whole entry) so that a connection opened for `addr` immediately
+    /// afterwards can't be allocated the same id as one still draining; see
+    /// `prune_conn_group`.
+    fn evict_conns(&mut self, addr: &SocketAddr) {
+        if let Some(group) = self.conns.get_mut(addr) {
+            let ids = std::mem::take(&mut group.ids);
+            group.high_since = None;
+            group.low_since = None;
+            for id in ids {
+                self.begin_drain(*addr, id);
+            }
+        }
+        self.prune_conn_group(addr);
+    }
+
+    /// Drops `addr`'s fan-out state once it's no longer needed: `addr` isn't
+    /// a known endpoint, has no open connections, and has nothing left
+    /// draining. Until then the entry (and its `next_id` counter) is kept so
+    /// that connection ids aren't reused while a same-keyed connection is
+    /// still draining.
+    fn prune_conn_group(&mut self, addr: &SocketAddr) {
+        if self.endpoints.contains_key(addr) {
+            return;
+        }
+        if self.conns.get(addr).is_some_and(|g| !g.ids.is_empty()) {
+            return;
+        }
+        if self.draining.keys().any(|(a, _)| a == addr) {
+            return;
+        }
+        self.conns.remove(addr);
+    }
+
+    /// Removes a connection from ready-selection, evicting it immediately if
+    /// it has no outstanding requests, or moving it into `draining` so that
+    /// in-flight requests can complete gracefully.
+    fn begin_drain(&mut self, addr: SocketAddr, id: usize) {
+        let in_flight = self.in_flight.get(&(addr, id)).copied().unwrap_or(0);
+        if in_flight == 0 {
+            tracing::debug!(%addr, id, "Closing connection");
+            self.pool.evict(&(addr, id));
+            self.metrics.connections.dec();
+            return;
+        }
+
+        tracing::debug!(%addr, id, in_flight, "Draining connection");
+        self.draining.insert(
+            (addr, id),
+            DrainState {
+                deadline: Instant::now() + self.drain_timeout,
+            },
+        );
+        self.metrics.draining.inc();
+    }
+
+    /// Evicts draining connections that have finished their in-flight
+    /// requests or have exceeded `drain_timeout`.
+    fn drive_draining(&mut self) {
+        let now = Instant::now();
+        let mut done = Vec::new();
+        for (&key, state) in self.draining.iter() {
+            let in_flight = self.in_flight.get(&key).copied().unwrap_or(0);
+            if now >= state.deadline || in_flight == 0 {
+                done.push(key);
+            }
+        }
+
+        for key in done {
+            let (addr, id) = key;
+            self.draining.remove(&key);
+            self.in_flight.remove(&key);
+            tracing::debug!(%addr, id, "Drained connection");
+            self.pool.evict(&key);
+            self.metrics.connections.dec();
+            self.metrics.draining.dec();
+            self.prune_conn_group(&addr);
+        }
+    }
+
+    /// Ensures `addr` has at least `min_per_endpoint` open connections.
+    fn ensure_min_conns(&mut self, addr: SocketAddr, target: &T) {
+        let min = self.scaling.min_per_endpoint.max(1);
+        let have = self.conns.get(&addr).map_or(0, |g| g.ids.len());
+        for _ in have..min {
+            self.push_conn(addr, target.clone());
+        }
+    }
+
+    /// Grows or shrinks each endpoint's connection count based on how its
+    /// ready connections' load ranks against the rest of the pool.
+    fn rescale_connections(&mut self) {
+        let max = self.scaling.max_per_endpoint.max(1);
+        let min = self.scaling.min_per_endpoint.max(1).min(max);
+        if max <= min {
+            return;
+        }
+
+        let mut loads: Vec<(SocketAddr, S::Metric)> = Vec::with_capacity(self.pool.ready_len());
+        for idx in 0..self.pool.ready_len() {
+            let (&(addr, id), svc) = self.pool.get_ready_index(idx).expect("invalid index");
+            if self.conns.contains_key(&addr) && !self.draining.contains_key(&(addr, id)) {
+                loads.push((addr, svc.load()));
+            }
+        }
+        if loads.is_empty() {
+            return;
+        }
+
+        // Rank loads pool-wide so that `high`/`low_watermark` fractions can
+        // be applied without requiring callers to construct a `Metric`
+        // value directly.
+        let mut ranked: Vec<&S::Metric> = loads.iter().map(|(_, m)| m).collect();
+        ranked.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let at_rank = |p: f64| -> &S::Metric {
+            let idx = ((ranked.len() - 1) as f64 * p.clamp(0.0, 1.0)).round() as usize;
+            ranked[idx]
+        };
+        let high = at_rank(self.scaling.high_watermark);
+        let low = at_rank(self.scaling.low_watermark);
+
+        let mut by_addr: AHashMap<SocketAddr, (bool, bool)> = AHashMap::new();
+        for (addr, load) in &loads {
+            let entry = by_addr.entry(*addr).or_insert((false, true));
+            entry.0 |= load > high;
+            entry.1 &= load < low;
+        }
+
+        let now = Instant::now();
+        for (addr, (is_high, is_low)) in by_addr {
+            let count = self.conns.get(&addr).map_or(0, |g| g.ids.len());
+            let action = match self.conns.get_mut(&addr) {
+                Some(group) if is_high && count < max => {
+                    group.low_since = None;
+                    let since = *group.high_since.get_or_insert(now);
+                    (now.duration_since(since) >= self.scaling.dwell).then(|| {
+                        group.high_since = None;
+                        true
+                    })
+                }
+                Some(group) if is_low && count > min => {
+                    group.high_since = None;
+                    let since = *group.low_since.get_or_insert(now);
+                    (now.duration_since(since) >= self.scaling.dwell).then(|| {
+                        group.low_since = None;
+                        false
+                    })
+                }
+                Some(group) => {
+                    group.high_since = None;
+                    group.low_since = None;
+                    None
+                }
+                None => None,
+            };
+
+            match action {
+                Some(true) => {
+                    if let Some(target) = self.endpoints.get(&addr).cloned() {
+                        tracing::debug!(%addr, connections = count + 1, "Scaling up endpoint");
+                        self.push_conn(addr, target);
+                    }
+                }
+                Some(false) => {
+                    tracing::debug!(%addr, connections = count - 1, "Scaling down endpoint");
+                    self.evict_one_conn(&addr);
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Drops an endpoint's outlier-detection state, releasing its ejection
+    /// slot (if it was ejected).
+    fn forget_outlier(&mut self, addr: &SocketAddr) {
+        if let Some(state) = self.outliers.remove(addr) {
+            if state.ejected_until.is_some() {
+                self.ejected = self.ejected.saturating_sub(1);
+                self.metrics.ejected.set(self.ejected as i64);
+            }
+        }
+    }
+
+    /// Drains outcomes reported by completed requests, decrementing their
+    /// connection's in-flight count and applying the outcome to the
+    /// endpoint's outlier-detection state, then re-admits any endpoints
+    /// whose ejection timer has elapsed.
+    fn drain_completions(&mut self) {
+        while let Ok((addr, id, success)) = self.outlier_rx.try_recv() {
+            if let Entry::Occupied(mut e) = self.in_flight.entry((addr, id)) {
+                *e.get_mut() = e.get().saturating_sub(1);
+                if *e.get() == 0 {
+                    e.remove();
+                }
+            }
+            self.record_outcome(addr, success);
+        }
+
+        let now = Instant::now();
+        for (addr, state) in self.outliers.iter_mut() {
+            if let Some(until) = state.ejected_until {
+                if now >= until {
+                    tracing::debug!(%addr, "Endpoint re-admitted for probing");
+                    state.ejected_until = None;
+                    // Start probing from a clean slate: a failure window (or
+                    // consecutive-failure count) accumulated before or during
+                    // ejection must not immediately re-trip either check on
+                    // the first post-probe request, regardless of its
+                    // outcome.
+                    state.window.clear();
+                    state.consecutive_failures = 0;
+                    self.ejected = self.ejected.saturating_sub(1);
+                }
+            }
+        }
+        self.metrics.ejected.set(self.ejected as i64);
+    }
+
+    /// Registers the task's waker against the nearest pending ejection
+    /// re-admission or drain deadline, so `poll_pool`/`poll_ready` are
+    /// re-invoked even if nothing else drives this pool's task -- e.g. a
+    /// pool whose only degraded endpoint was just ejected and which is
+    /// otherwise idle.
+    fn arm_next_deadline(&mut self, cx: &mut Context<'_>) {
+        let next = self
+            .outliers
+            .values()
+            .filter_map(|state| state.ejected_until)
+            .chain(self.draining.values().map(|state| state.deadline))
+            .min();
+
+        if let Some(deadline) = next {
+            self.timer.as_mut().reset(deadline);
+            let _ = self.timer.as_mut().poll(cx);
+        }
+    }
+
+    /// Records the outcome of a completed request against the named
+    /// endpoint's outlier state, ejecting it if it has crossed a failure
+    /// threshold.
+    fn record_outcome(&mut self, addr: SocketAddr, success: bool) {
+        let Some(state) = self.outliers.get_mut(&addr) else {
+            return;
+        };
+        let was_ejected = state.ejected_until.is_some();
+
+        if success {
+            state.consecutive_failures = 0;
+            if !was_ejected {
+                // A success while probing clears the endpoint's ejection
+                // history.
+                state.ejection_count = 0;
+            }
+        } else {
+            state.consecutive_failures += 1;
+        }
+
+        state.window.push_back(success);
+        if state.window.len() > self.outlier_config.window_size {
+            state.window.pop_front();
+        }
+
+        if success || was_ejected {
+            return;
+        }
+
+        let exceeds_consecutive =
+            state.consecutive_failures >= self.outlier_config.consecutive_failures;
+        let exceeds_rate = state.window.len() as u32 >= self.outlier_config.min_requests_in_window
+            && {
+                let failures = state.window.iter().filter(|ok| !**ok).count() as f64;
+                failures / state.window.len() as f64 >= self.outlier_config.error_rate_threshold
+            };
+        if !exceeds_consecutive && !exceeds_rate {
+            return;
+        }
+
+        let max_ejected = (self.endpoints.len() as f64 * self.outlier_config.max_ejection_percent)
+            .floor() as usize;
+        if self.ejected >= max_ejected {
+            tracing::debug!(%addr, "Endpoint eligible for ejection but pool ejection limit reached");
+            return;
+        }
+
+        // Never eject the last non-ejected endpoint: doing so would leave
+        // the pool with nothing to serve traffic from, regardless of what
+        // the configured percentage allows.
+        if self.ejected + 1 >= self.endpoints.len() {
+            tracing::debug!(%addr, "Endpoint eligible for ejection but it is the last healthy host");
+            return;
+        }
+
+        state.consecutive_failures = 0;
+        state.ejection_count += 1;
+        let backoff = self
+            .outlier_config
+            .base_ejection_time
+            .saturating_mul(state.ejection_count)
+            .min(self.outlier_config.max_ejection_time);
+        state.ejected_until = Some(Instant::now() + backoff);
+        self.ejected += 1;
+        self.metrics.ejections.inc();
+        self.metrics.ejected.set(self.ejected as i64);
+        tracing::debug!(%addr, count = state.ejection_count, ?backoff, "Ejecting endpoint");
+    }
+
+    fn is_ejected(&self, addr: &SocketAddr) -> bool {
+        self.outliers
+            .get(addr)
+            .is_some_and(|state| state.ejected_until.is_some())
+    }
+
+    /// Returns whether a cached `next_idx` selection is still eligible to be
+    /// reused, i.e. its endpoint hasn't been ejected or started draining
+    /// since it was selected.
+    ///
+    /// `poll_ready` may run `drain_completions` (which can eject an
+    /// endpoint) between the poll that populated `next_idx` and the poll
+    /// that would otherwise reuse it, so the cache must be re-validated
+    /// rather than trusted outright.
+    fn cached_index_is_eligible(&mut self, idx: usize) -> bool {
+        match self.pool.get_ready_index(idx) {
+            Some((&(addr, id), _)) => {
+                !self.is_ejected(&addr) && !self.draining.contains_key(&(addr, id))
+            }
+            None => false,
+        }
+    }
+
     fn p2c_ready_index(&mut self) -> Option<usize> {
-        match self.pool.ready_len() {
-            0 => None,
-            1 => Some(0),
-            len => {
-                let (aidx, bidx) = gen_pair(&mut self.rng, len);
-                let aload = self.ready_index_load(aidx);
-                let bload = self.ready_index_load(bidx);
-                let chosen = if aload <= bload { aidx } else { bidx };
-                tracing::trace!(
-                    a.index = aidx,
-                    a.load = ?aload,
-                    b.index = bidx,
-                    b.load = ?bload,
-                    chosen = if chosen == aidx { "a" } else { "b" },
-                    "p2c",
-                );
-                Some(chosen)
+        let len = self.pool.ready_len();
+        if len == 0 {
+            return None;
+        }
+
+        if self.ejected == 0 && self.draining.is_empty() {
+            // Fast path: nothing is ejected or draining, so every ready
+            // index is eligible. This is the overwhelmingly common case
+            // (it runs on every proxied request), so sample directly
+            // rather than paying for a filter pass over the whole ready
+            // set just to rebuild the identity mapping.
+            if len == 1 {
+                return Some(0);
+            }
+            let k = self.k.min(len);
+            let (chosen, chosen_load) = if k == 2 {
+                let (a, b) = gen_pair(&mut self.rng, len);
+                self.min_load_index(&[a, b])
+            } else {
+                let sample = gen_k(&mut self.rng, len, k);
+                self.min_load_index(&sample)
+            };
+            tracing::trace!(k, chosen.index = chosen, chosen.load = ?chosen_load, "p2c");
+            return Some(chosen);
+        }
+
+        // Slow path: some ready endpoints are ejected or draining.
+        // Reservoir-sample up to `k` eligible indices in a single pass
+        // over the ready set, rather than collecting the full eligible
+        // set just to then sub-sample it.
+        let k = self.k;
+        let mut sample: Vec<usize> = Vec::with_capacity(k.min(len));
+        let mut eligible_seen = 0usize;
+        for idx in 0..len {
+            let (&(addr, id), _) = self.pool.get_ready_index(idx).expect("invalid index");
+            if self.is_ejected(&addr) || self.draining.contains_key(&(addr, id)) {
+                continue;
+            }
+            if eligible_seen < k {
+                sample.push(idx);
+            } else {
+                let r = self.rng.gen_range(0..=eligible_seen);
+                if r < k {
+                    sample[r] = idx;
+                }
+            }
+            eligible_seen += 1;
+        }
+
+        if sample.is_empty() {
+            return None;
+        }
+        let (chosen, chosen_load) = self.min_load_index(&sample);
+        tracing::trace!(k, sample.len = sample.len(), chosen.index = chosen, chosen.load = ?chosen_load, "p2c");
+        Some(chosen)
+    }
+
+    /// Returns the index with the lowest load among `candidates`.
+    fn min_load_index(&self, candidates: &[usize]) -> (usize, S::Metric) {
+        let mut chosen = candidates[0];
+        let mut chosen_load = self.ready_index_load(chosen);
+        for &idx in &candidates[1..] {
+            let load = self.ready_index_load(idx);
+            if load < chosen_load {
+                chosen = idx;
+                chosen_load = load;
             }
         }
+        (chosen, chosen_load)
     }
 
     /// Accesses a ready endpoint by index and returns its current load.
@@ -200,10 +846,11 @@ where
     }
 }
 
+/// Zero-allocation fast path for `k == 2` (power-of-two-choices), by far
+/// the most common configuration. Returns two distinct indices in
+/// `0..len`, in a random order.
 fn gen_pair(rng: &mut SmallRng, len: usize) -> (usize, usize) {
     debug_assert!(len >= 2, "must have at least two endpoints");
-    // Get two distinct random indexes (in a random order) and
-    // compare the loads of the service at each index.
     let aidx = rng.gen_range(0..len);
     let mut bidx = rng.gen_range(0..(len - 1));
     if bidx >= aidx {
@@ -213,6 +860,127 @@ fn gen_pair(rng: &mut SmallRng, len: usize) -> (usize, usize) {
     (aidx, bidx)
 }
 
+/// The realistic range of `k` for power-of-k-choices; selections at or
+/// below this size are tracked inline instead of on the heap.
+const INLINE_K: usize = 8;
+
+/// A fixed-capacity, inline-first buffer of the indices sampled by
+/// [`gen_k`]. `k` is realistically tiny (2-8), so this avoids a heap
+/// allocation for every selection; it only spills to the heap if `k`
+/// exceeds [`INLINE_K`].
+enum SampleBuf {
+    Inline([usize; INLINE_K], usize),
+    Heap(Vec<usize>),
+}
+
+impl SampleBuf {
+    fn with_capacity(k: usize) -> Self {
+        if k <= INLINE_K {
+            SampleBuf::Inline([0; INLINE_K], 0)
+        } else {
+            SampleBuf::Heap(Vec::with_capacity(k))
+        }
+    }
+
+    fn push(&mut self, idx: usize) {
+        match self {
+            SampleBuf::Inline(buf, len) => {
+                buf[*len] = idx;
+                *len += 1;
+            }
+            SampleBuf::Heap(vec) => vec.push(idx),
+        }
+    }
+}
+
+impl std::ops::Deref for SampleBuf {
+    type Target = [usize];
+    fn deref(&self) -> &[usize] {
+        match self {
+            SampleBuf::Inline(buf, len) => &buf[..*len],
+            SampleBuf::Heap(vec) => vec,
+        }
+    }
+}
+
+/// Sparse swap-tracking buffer for partial Fisher–Yates sampling: only
+/// the (at most `k`) positions that are actually swapped are recorded.
+/// For the realistic range of `k`, a linearly-scanned inline buffer is
+/// faster than an `AHashMap`, which pays for hashing and a heap
+/// allocation on every selection to track a handful of entries.
+enum SwapBuf {
+    Inline([(usize, usize); INLINE_K], usize),
+    Heap(Vec<(usize, usize)>),
+}
+
+impl SwapBuf {
+    fn with_capacity(k: usize) -> Self {
+        if k <= INLINE_K {
+            SwapBuf::Inline([(0, 0); INLINE_K], 0)
+        } else {
+            SwapBuf::Heap(Vec::with_capacity(k))
+        }
+    }
+
+    fn get(&self, key: usize) -> Option<usize> {
+        let entries: &[(usize, usize)] = match self {
+            SwapBuf::Inline(buf, len) => &buf[..*len],
+            SwapBuf::Heap(vec) => vec,
+        };
+        entries.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+
+    /// Inserts `key -> value`, overwriting any existing entry for `key`.
+    fn insert(&mut self, key: usize, value: usize) {
+        match self {
+            SwapBuf::Inline(buf, len) => {
+                if let Some(slot) = buf[..*len].iter_mut().find(|(k, _)| *k == key) {
+                    slot.1 = value;
+                } else {
+                    buf[*len] = (key, value);
+                    *len += 1;
+                }
+            }
+            SwapBuf::Heap(vec) => {
+                if let Some(slot) = vec.iter_mut().find(|(k, _)| *k == key) {
+                    slot.1 = value;
+                } else {
+                    vec.push((key, value));
+                }
+            }
+        }
+    }
+}
+
+/// Draws `k` distinct indices from `0..len` using partial Fisher–Yates
+/// reservoir sampling.
+///
+/// For `k == 2`, prefer [`gen_pair`]'s zero-allocation fast path. For
+/// larger `k`, the swapped positions and sampled indices are tracked in
+/// small inline buffers rather than a `Vec`/`AHashMap` pair, so sampling
+/// stays allocation-free for the realistic range of `k`.
+fn gen_k(rng: &mut SmallRng, len: usize, k: usize) -> SampleBuf {
+    debug_assert!(k >= 1 && k <= len, "k must be in 1..=len");
+    let mut swapped = SwapBuf::with_capacity(k);
+    let mut sample = SampleBuf::with_capacity(k);
+    for i in 0..k {
+        let j = rng.gen_range(i..len);
+        let vi = swapped.get(i).unwrap_or(i);
+        let vj = swapped.get(j).unwrap_or(j);
+        sample.push(vj);
+        swapped.insert(j, vi);
+    }
+    debug_assert_eq!(
+        sample
+            .iter()
+            .collect::<std::collections::HashSet<_>>()
+            .len(),
+        k,
+        "sampled indices must be distinct"
+    );
+    sample
+}
+
 impl<T, N, Req, S> Pool<T, Req> for P2cPool<T, N, Req, S>
 where
     T: Clone + Eq + std::fmt::Debug,
@@ -244,6 +1012,10 @@ where
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Result<(), Self::Error>> {
         tracing::trace!("Polling pending");
+        self.drain_completions();
+        self.drive_draining();
+        self.rescale_connections();
+        self.arm_next_deadline(cx);
         self.pool.poll_pending(cx).map_err(|Failed(_, e)| e)
     }
 }
@@ -259,17 +1031,21 @@ where
 {
     type Response = S::Response;
     type Error = Error;
-    type Future = futures::future::ErrInto<S::Future, Error>;
+    type Future = futures::future::ErrInto<Tracked<S::Future>, Error>;
 
     /// Returns ready when at least one endpoint is ready.
     ///
-    /// If multiple endpoints are ready, the power-of-two-choices algorithm is
+    /// If multiple endpoints are ready, the power-of-k-choices algorithm is
     /// used to select one.
     ///
     /// NOTE that this may return `Pending` when there are no endpoints. In such
     /// cases, the caller must invoke `update_pool` and then wait for new
     /// endpoints to become ready.
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.drain_completions();
+        self.drive_draining();
+        self.rescale_connections();
+        self.arm_next_deadline(cx);
         loop {
             tracing::trace!(pending = self.pool.pending_len(), "Polling pending");
             match self.pool.poll_pending(cx)? {
@@ -277,7 +1053,8 @@ where
                 Poll::Pending => tracing::trace!("Endpoints are pending"),
             }
 
-            let idx = match self.next_idx.take().or_else(|| self.p2c_ready_index()) {
+            let cached = self.next_idx.take().filter(|&idx| self.cached_index_is_eligible(idx));
+            let idx = match cached.or_else(|| self.p2c_ready_index()) {
                 Some(idx) => idx,
                 None => {
                     tracing::debug!("No ready endpoints");
@@ -299,7 +1076,72 @@ where
 
     fn call(&mut self, req: Req) -> Self::Future {
         let idx = self.next_idx.take().expect("call before ready");
-        self.pool.call_ready_index(idx, req).err_into()
+        let (&(addr, id), _) = self.pool.get_ready_index(idx).expect("invalid index");
+        *self.in_flight.entry((addr, id)).or_insert(0) += 1;
+        let inner = self.pool.call_ready_index(idx, req);
+        Tracked {
+            inner,
+            guard: InFlightGuard {
+                addr,
+                id,
+                tx: self.outlier_tx.clone(),
+                completed: false,
+            },
+        }
+        .err_into()
+    }
+}
+
+pin_project! {
+    /// Wraps an endpoint's response future so that its outcome is reported
+    /// back to the pool's passive outlier detector, and its in-flight count
+    /// decremented, once it completes.
+    pub struct Tracked<F> {
+        #[pin]
+        inner: F,
+        guard: InFlightGuard,
+    }
+}
+
+/// Reports a request as failed (and releases its `in_flight` slot) when its
+/// [`Tracked`] future drops before completing (e.g. the client disconnected,
+/// or an upstream timeout fired). A request that completes normally has
+/// already been reported by `Tracked::poll`, so nothing is sent on drop.
+///
+/// `pin_project_lite` doesn't support pinned `Drop` impls, so this lives in
+/// its own unpinned field rather than on `Tracked` itself: dropping `Tracked`
+/// still drops its non-projected fields normally, which is all a cleanup
+/// hook here needs.
+struct InFlightGuard {
+    addr: SocketAddr,
+    id: usize,
+    tx: mpsc::UnboundedSender<(SocketAddr, usize, bool)>,
+    completed: bool,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if !self.completed {
+            // The request was dropped before completing; report it as a
+            // failure so `in_flight` is released and the endpoint's outlier
+            // state reflects the cancellation.
+            let _ = self.tx.send((self.addr, self.id, false));
+        }
+    }
+}
+
+impl<F> Future for Tracked<F>
+where
+    F: TryFuture,
+{
+    type Output = Result<F::Ok, F::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.try_poll(cx));
+        this.guard.completed = true;
+        let _ = this.guard.tx.send((this.guard.addr, this.guard.id, res.is_ok()));
+        Poll::Ready(res)
     }
 }
 
@@ -327,7 +1169,42 @@ where
             updates.clone(),
         );
 
-        Self { endpoints, updates }
+        let ejections = prom::Family::default();
+        reg.register(
+            "ejections",
+            "The total number of times an endpoint has been ejected by passive outlier detection",
+            ejections.clone(),
+        );
+
+        let ejected = prom::Family::default();
+        reg.register(
+            "ejected",
+            "The number of endpoints currently ejected by passive outlier detection",
+            ejected.clone(),
+        );
+
+        let connections = prom::Family::default();
+        reg.register(
+            "connections",
+            "The total number of open connections across all endpoints in the balancer",
+            connections.clone(),
+        );
+
+        let draining = prom::Family::default();
+        reg.register(
+            "draining",
+            "The number of connections removed from the balancer that are still draining in-flight requests",
+            draining.clone(),
+        );
+
+        Self {
+            endpoints,
+            updates,
+            ejections,
+            ejected,
+            connections,
+            draining,
+        }
     }
 
     pub fn metrics<'l>(&self, labels: &'l L) -> P2cMetrics
@@ -351,12 +1228,20 @@ where
             .updates
             .get_or_create(&(Update::DoesNotExist, labels).into())
             .clone();
+        let ejections: prom::Counter = self.ejections.get_or_create(labels).clone();
+        let ejected: prom::Gauge = self.ejected.get_or_create(labels).clone();
+        let connections: prom::Gauge = self.connections.get_or_create(labels).clone();
+        let draining: prom::Gauge = self.draining.get_or_create(labels).clone();
         P2cMetrics {
             endpoints,
             updates_reset,
             updates_add,
             updates_rm,
             updates_dne,
+            ejections,
+            ejected,
+            connections,
+            draining,
         }
     }
 }
@@ -375,6 +1260,38 @@ impl P2cMetrics {
     }
 }
 
+// === impl OutlierDetectorConfig ===
+
+impl Default for OutlierDetectorConfig {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            window_size: 10,
+            min_requests_in_window: 10,
+            error_rate_threshold: 0.5,
+            base_ejection_time: Duration::from_secs(30),
+            max_ejection_time: Duration::from_secs(300),
+            max_ejection_percent: 0.5,
+        }
+    }
+}
+
+// === impl ConnectionScalingConfig ===
+
+impl Default for ConnectionScalingConfig {
+    /// Disables connection scaling: exactly one connection is maintained per
+    /// endpoint.
+    fn default() -> Self {
+        Self {
+            min_per_endpoint: 1,
+            max_per_endpoint: 1,
+            high_watermark: 0.8,
+            low_watermark: 0.2,
+            dwell: Duration::from_secs(10),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -382,19 +1299,68 @@ mod tests {
     use futures::prelude::*;
     use linkerd_stack::ServiceExt;
     use parking_lot::Mutex;
-    use std::sync::Arc;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
     use tokio::time;
     use tokio_test::{assert_pending, assert_ready_ok};
     use tower::load::{CompleteOnResponse, PeakEwma};
 
+    /// A service with a directly-settable load, shared via an `Arc`. Used
+    /// to deterministically drive `rescale_connections`'s watermark
+    /// comparisons, which `PeakEwma`'s latency-derived load can't do.
+    #[derive(Clone)]
+    struct ConstLoad(Arc<AtomicU64>);
+
+    impl ConstLoad {
+        fn new(load: Arc<AtomicU64>) -> Self {
+            Self(load)
+        }
+    }
+
+    impl Service<()> for ConstLoad {
+        type Response = ();
+        type Error = std::convert::Infallible;
+        type Future = std::future::Ready<Result<(), std::convert::Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, (): ()) -> Self::Future {
+            std::future::ready(Ok(()))
+        }
+    }
+
+    impl Load for ConstLoad {
+        type Metric = u64;
+
+        fn load(&self) -> u64 {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
     quickcheck::quickcheck! {
+        fn gen_k_distinct(len: usize, k: usize) -> quickcheck::TestResult {
+            if len == 0 || k == 0 || k > len {
+                return quickcheck::TestResult::discard();
+            }
+            let mut rng = SmallRng::from_rng(rand::thread_rng()).expect("rng");
+            let sample = gen_k(&mut rng, len, k);
+            let unique = sample.iter().collect::<HashSet<_>>().len();
+            quickcheck::TestResult::from_bool(
+                sample.len() == k && unique == k && sample.iter().all(|&i| i < len),
+            )
+        }
+
         fn gen_pair_distinct(len: usize) -> quickcheck::TestResult {
             if len < 2 {
                 return quickcheck::TestResult::discard();
             }
             let mut rng = SmallRng::from_rng(rand::thread_rng()).expect("rng");
             let (aidx, bidx) = gen_pair(&mut rng, len);
-            quickcheck::TestResult::from_bool(aidx != bidx)
+            quickcheck::TestResult::from_bool(aidx != bidx && aidx < len && bidx < len)
         }
     }
 
@@ -407,17 +1373,24 @@ mod tests {
 
         let seen = Arc::new(Mutex::new(HashSet::<(SocketAddr, usize)>::default()));
         let metrics = P2cMetrics::default();
-        let mut pool = P2cPool::new(metrics.clone(), |(addr, n): (SocketAddr, usize)| {
-            assert!(seen.lock().insert((addr, n)));
-            PeakEwma::new(
-                linkerd_stack::service_fn(|()| {
-                    std::future::ready(Ok::<_, std::convert::Infallible>(()))
-                }),
-                time::Duration::from_secs(1),
-                1.0 * 1000.0 * 1000.0,
-                CompleteOnResponse::default(),
-            )
-        });
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(addr, n): (SocketAddr, usize)| {
+                assert!(seen.lock().insert((addr, n)));
+                PeakEwma::new(
+                    linkerd_stack::service_fn(|()| {
+                        std::future::ready(Ok::<_, std::convert::Infallible>(()))
+                    }),
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
 
         pool.update_pool(Update::Reset(vec![(addr0, 0)]));
         assert_eq!(pool.endpoints.len(), 1);
@@ -499,22 +1472,29 @@ mod tests {
         h2.allow(0);
 
         let metrics = P2cMetrics::default();
-        let mut pool = P2cPool::new(metrics, |(a, ())| {
-            PeakEwma::new(
-                if a == addr0 {
-                    svc0.clone()
-                } else if a == addr1 {
-                    svc1.clone()
-                } else if a == addr2 {
-                    svc2.clone()
-                } else {
-                    panic!("unexpected address: {a}");
-                },
-                time::Duration::from_secs(1),
-                1.0 * 1000.0 * 1000.0,
-                CompleteOnResponse::default(),
-            )
-        });
+        let mut pool = P2cPool::new(
+            metrics,
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else if a == addr1 {
+                        svc1.clone()
+                    } else if a == addr2 {
+                        svc2.clone()
+                    } else {
+                        panic!("unexpected address: {a}");
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
 
         assert!(pool.ready().now_or_never().is_none());
         assert!(pool.next_idx.is_none());
@@ -563,4 +1543,804 @@ mod tests {
         assert_eq!(pool.pool.ready_len(), 3);
         assert_eq!(pool.pool.pending_len(), 0);
     }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn p2c_ready_index_k_clamped() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        // A sample size larger than the pool should be clamped to the
+        // number of ready endpoints, so selection always considers both.
+        let mut pool = P2cPool::new(
+            metrics,
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else {
+                        svc1.clone()
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            8,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        h0.allow(1);
+        h1.allow(1);
+
+        assert!(pool.ready().now_or_never().is_some());
+        assert!(pool.next_idx.is_some());
+        assert_eq!(pool.pool.ready_len(), 2);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn connection_scaling_min_per_endpoint() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, _h0) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(_a, ())| {
+                PeakEwma::new(
+                    svc0.clone(),
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig {
+                min_per_endpoint: 2,
+                max_per_endpoint: 2,
+                ..ConnectionScalingConfig::default()
+            },
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ())]));
+        assert_eq!(metrics.endpoints.get(), 1, "one distinct address");
+        assert_eq!(
+            metrics.connections.get(),
+            2,
+            "two connections to that address"
+        );
+        assert_eq!(pool.pool.ready_len() + pool.pool.pending_len(), 2);
+
+        pool.update_pool(Update::DoesNotExist);
+        assert_eq!(metrics.endpoints.get(), 0);
+        assert_eq!(metrics.connections.get(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn connection_scaling_watermarks() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        // addr0 is the endpoint under test; its load is driven directly.
+        // addr1 is a fixed-load anchor with several ready connections, so
+        // both watermark ranks land on one of the anchor's connections
+        // rather than on addr0's, and addr0's load is compared against the
+        // anchor's.
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+
+        let test_load = Arc::new(AtomicU64::new(100));
+        let anchor_load = Arc::new(AtomicU64::new(100));
+        let test_load2 = test_load.clone();
+        let anchor_load2 = anchor_load.clone();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            move |(addr, ())| {
+                if addr == addr0 {
+                    ConstLoad::new(test_load2.clone())
+                } else {
+                    ConstLoad::new(anchor_load2.clone())
+                }
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig {
+                min_per_endpoint: 1,
+                max_per_endpoint: 5,
+                high_watermark: 0.8,
+                low_watermark: 0.2,
+                dwell: time::Duration::from_millis(100),
+            },
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        pool.push_conn(addr1, ());
+        pool.push_conn(addr1, ());
+        pool.push_conn(addr1, ());
+        let ctx = &mut Context::from_waker(futures_util::task::noop_waker_ref());
+        assert_ready_ok!(pool.poll_pool(ctx));
+        assert_eq!(pool.conns.get(&addr0).unwrap().ids.len(), 1);
+        assert_eq!(pool.conns.get(&addr1).unwrap().ids.len(), 4);
+
+        // Drive addr0's load above the high watermark and hold it there
+        // for `dwell`; a connection should be opened.
+        test_load.store(5000, Ordering::SeqCst);
+        pool.rescale_connections();
+        assert_eq!(
+            pool.conns.get(&addr0).unwrap().ids.len(),
+            1,
+            "dwell hasn't elapsed yet"
+        );
+        time::advance(time::Duration::from_millis(100)).await;
+        pool.rescale_connections();
+        assert_eq!(
+            pool.conns.get(&addr0).unwrap().ids.len(),
+            2,
+            "scaled up after dwell"
+        );
+        assert_eq!(metrics.connections.get(), 6);
+
+        // Drive addr0's load below the low watermark and hold it there
+        // for `dwell`; a connection should be closed.
+        test_load.store(50, Ordering::SeqCst);
+        pool.rescale_connections();
+        assert_eq!(
+            pool.conns.get(&addr0).unwrap().ids.len(),
+            2,
+            "dwell hasn't elapsed yet"
+        );
+        time::advance(time::Duration::from_millis(100)).await;
+        pool.rescale_connections();
+        assert_eq!(
+            pool.conns.get(&addr0).unwrap().ids.len(),
+            1,
+            "scaled down after dwell"
+        );
+        assert_eq!(metrics.connections.get(), 5);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn passive_outlier_ejection_never_ejects_last_host() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            move |(_, ())| {
+                PeakEwma::new(
+                    svc0.clone(),
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig {
+                consecutive_failures: 2,
+                // Allows ejecting the whole pool, so that only the last-host
+                // guard can keep the endpoint in service.
+                max_ejection_percent: 1.0,
+                base_ejection_time: time::Duration::from_secs(10),
+                max_ejection_time: time::Duration::from_secs(60),
+                ..OutlierDetectorConfig::default()
+            },
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ())]));
+        h0.allow(2);
+
+        // Fail twice in a row against the pool's only endpoint. It would
+        // normally be ejected, but doing so would leave the pool with no
+        // healthy host to serve traffic from, so it must stay in service.
+        for _ in 0..2 {
+            assert!(pool.ready().now_or_never().is_some());
+            let call = pool.call(());
+            let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+            respond.send_error("backend error");
+            let _ = call.now_or_never().unwrap();
+        }
+        pool.drain_completions();
+        assert_eq!(metrics.ejections.get(), 0);
+        assert_eq!(metrics.ejected.get(), 0);
+        assert!(!pool.is_ejected(&addr0));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn passive_outlier_ejection() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else {
+                        svc1.clone()
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig {
+                consecutive_failures: 2,
+                base_ejection_time: time::Duration::from_secs(10),
+                max_ejection_time: time::Duration::from_secs(60),
+                ..OutlierDetectorConfig::default()
+            },
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        h0.allow(2);
+        h1.allow(0);
+
+        // Fail twice in a row against addr0; it should be ejected and the
+        // only remaining ready endpoint should be addr1.
+        for _ in 0..2 {
+            assert!(pool.ready().now_or_never().is_some());
+            let call = pool.call(());
+            let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+            respond.send_error("backend error");
+            let _ = call.now_or_never().unwrap();
+        }
+        pool.drain_completions();
+        assert_eq!(metrics.ejections.get(), 1);
+        assert_eq!(metrics.ejected.get(), 1);
+        assert!(pool.is_ejected(&addr0));
+
+        // While ejected, only addr1 may be selected.
+        h1.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        assert_eq!(pool.next_idx, Some(pool.pool.ready_len() - 1));
+
+        // After the ejection timer elapses, addr0 is re-admitted (probing).
+        time::advance(time::Duration::from_secs(10)).await;
+        pool.drain_completions();
+        assert!(!pool.is_ejected(&addr0));
+        assert_eq!(metrics.ejected.get(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn poll_ready_revalidates_cached_selection_after_ejection() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics,
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else {
+                        svc1.clone()
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig {
+                consecutive_failures: 1,
+                ..OutlierDetectorConfig::default()
+            },
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        h0.allow(1);
+        h1.allow(0);
+        // addr1 never becomes ready, so addr0 is the only eligible
+        // selection and gets cached in `next_idx`.
+        assert!(pool.ready().now_or_never().is_some());
+        assert!(pool.next_idx.is_some());
+
+        // Simulate another in-flight request against addr0 completing with
+        // a failure between this poll and the next one, tripping the
+        // ejection -- without going through `call`/`next_idx`, so the cache
+        // is left stale.
+        pool.record_outcome(addr0, false);
+        assert!(pool.is_ejected(&addr0));
+
+        // The stale cached selection must not be dispatched to the
+        // now-ejected addr0: with no other endpoint ready, poll_ready must
+        // return Pending rather than reusing it.
+        assert!(pool.ready().now_or_never().is_none());
+        assert!(pool.next_idx.is_none());
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn passive_outlier_probe_clears_stale_window() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else {
+                        svc1.clone()
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig {
+                consecutive_failures: 100,
+                window_size: 10,
+                min_requests_in_window: 3,
+                error_rate_threshold: 0.5,
+                base_ejection_time: time::Duration::from_secs(10),
+                max_ejection_time: time::Duration::from_secs(60),
+                ..OutlierDetectorConfig::default()
+            },
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        h0.allow(3);
+        h1.allow(0);
+
+        // Three failures against addr0 trip the error-rate check (3/3 >=
+        // 50%, meeting the configured minimum window size) and eject it.
+        for _ in 0..3 {
+            assert!(pool.ready().now_or_never().is_some());
+            let call = pool.call(());
+            let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+            respond.send_error("backend error");
+            let _ = call.now_or_never().unwrap();
+        }
+        pool.drain_completions();
+        assert_eq!(metrics.ejections.get(), 1);
+        assert!(pool.is_ejected(&addr0));
+
+        // After the ejection timer elapses, addr0 is re-admitted for
+        // probing.
+        time::advance(time::Duration::from_secs(10)).await;
+        pool.drain_completions();
+        assert!(!pool.is_ejected(&addr0));
+
+        // A single post-probe failure must not immediately re-eject addr0
+        // on the strength of its stale pre-ejection failure history: the
+        // window must have been reset on re-admission, so one fresh
+        // failure alone can't meet `min_requests_in_window`.
+        h0.allow(1);
+        h1.allow(0);
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+        respond.send_error("backend error");
+        let _ = call.now_or_never().unwrap();
+        pool.drain_completions();
+        assert_eq!(
+            metrics.ejections.get(),
+            1,
+            "a single fresh failure shouldn't re-trip the rate check using stale history"
+        );
+        assert!(!pool.is_ejected(&addr0));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn ejection_backoff_arms_a_wakeup() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else {
+                        svc1.clone()
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig {
+                consecutive_failures: 1,
+                base_ejection_time: time::Duration::from_secs(10),
+                max_ejection_time: time::Duration::from_secs(10),
+                ..OutlierDetectorConfig::default()
+            },
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        h0.allow(1);
+        // addr1 is never made ready, so once addr0 is ejected the pool has
+        // no eligible endpoint and nothing else will ever re-poll it.
+        h1.allow(0);
+
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+        respond.send_error("backend error");
+        let _ = call.now_or_never().unwrap();
+        pool.drain_completions();
+        assert!(pool.is_ejected(&addr0));
+
+        struct Flag(std::sync::Arc<std::sync::atomic::AtomicBool>);
+        impl std::task::Wake for Flag {
+            fn wake(self: std::sync::Arc<Self>) {
+                self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+        let woken = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let waker = std::task::Waker::from(std::sync::Arc::new(Flag(woken.clone())));
+        let ctx = &mut Context::from_waker(&waker);
+
+        assert_pending!(pool.poll_ready(ctx));
+        assert!(!woken.load(std::sync::atomic::Ordering::SeqCst));
+
+        // Nothing else drives this pool; only its own armed timer can wake
+        // it once the ejection backoff elapses.
+        time::advance(time::Duration::from_secs(10)).await;
+        assert!(
+            woken.load(std::sync::atomic::Ordering::SeqCst),
+            "the ejection backoff elapsing should wake the task on its own"
+        );
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn passive_outlier_probe_clears_stale_consecutive_failures() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let addr1 = "192.168.10.11:80".parse().unwrap();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(a, ())| {
+                PeakEwma::new(
+                    if a == addr0 {
+                        svc0.clone()
+                    } else {
+                        svc1.clone()
+                    },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig {
+                consecutive_failures: 2,
+                // Large enough that the error-rate check never trips,
+                // isolating the consecutive-failures path tested here.
+                window_size: 10,
+                min_requests_in_window: 100,
+                error_rate_threshold: 0.5,
+                base_ejection_time: time::Duration::from_secs(10),
+                max_ejection_time: time::Duration::from_secs(60),
+                ..OutlierDetectorConfig::default()
+            },
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(10),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ()), (addr1, ())]));
+        h0.allow(2);
+        h1.allow(0);
+
+        // Two consecutive failures against addr0 trip the consecutive-
+        // failures check and eject it.
+        for _ in 0..2 {
+            assert!(pool.ready().now_or_never().is_some());
+            let call = pool.call(());
+            let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+            respond.send_error("backend error");
+            let _ = call.now_or_never().unwrap();
+        }
+        pool.drain_completions();
+        assert_eq!(metrics.ejections.get(), 1);
+        assert!(pool.is_ejected(&addr0));
+
+        // After the ejection timer elapses, addr0 is re-admitted for
+        // probing.
+        time::advance(time::Duration::from_secs(10)).await;
+        pool.drain_completions();
+        assert!(!pool.is_ejected(&addr0));
+
+        // A single post-probe failure must not immediately re-eject addr0
+        // on the strength of its stale pre-ejection consecutive-failure
+        // count: that count must have been reset to zero on re-admission,
+        // so one fresh failure alone can't meet `consecutive_failures`.
+        h0.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+        respond.send_error("backend error");
+        let _ = call.now_or_never().unwrap();
+        pool.drain_completions();
+        assert_eq!(
+            metrics.ejections.get(),
+            1,
+            "a single fresh failure shouldn't re-trip the consecutive-failures \
+             check using a stale count"
+        );
+        assert!(!pool.is_ejected(&addr0));
+
+        // A second fresh failure does meet the threshold, confirming the
+        // counter still works normally after being reset.
+        h0.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+        respond.send_error("backend error");
+        let _ = call.now_or_never().unwrap();
+        pool.drain_completions();
+        assert_eq!(metrics.ejections.get(), 2);
+        assert!(pool.is_ejected(&addr0));
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn graceful_drain_of_evicted_endpoint() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(_a, ())| {
+                PeakEwma::new(
+                    svc0.clone(),
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(30),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ())]));
+        h0.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond) = h0.next_request().now_or_never().unwrap().unwrap();
+
+        // Service discovery removes the endpoint while the request above is
+        // still outstanding; the connection must not be evicted out from
+        // under it.
+        pool.update_pool(Update::DoesNotExist);
+        assert_eq!(metrics.endpoints.get(), 0);
+        assert_eq!(metrics.connections.get(), 1, "draining connection stays open");
+        assert_eq!(metrics.draining.get(), 1);
+        assert_eq!(pool.pool.ready_len() + pool.pool.pending_len(), 1);
+
+        // The draining connection isn't eligible for selection.
+        assert!(pool.ready().now_or_never().is_none());
+
+        // Once the in-flight request completes, the connection is drained.
+        respond.send_response(());
+        call.now_or_never()
+            .expect("call should be satisfied")
+            .expect("call should succeed");
+
+        let ctx = &mut Context::from_waker(futures_util::task::noop_waker_ref());
+        assert_ready_ok!(pool.poll_pool(ctx));
+        assert_eq!(metrics.connections.get(), 0);
+        assert_eq!(metrics.draining.get(), 0);
+        assert_eq!(pool.pool.ready_len() + pool.pool.pending_len(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn cancelled_call_releases_in_flight() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(_a, ())| {
+                PeakEwma::new(
+                    svc0.clone(),
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(30),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ())]));
+        h0.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let (_req, _respond) = h0.next_request().now_or_never().unwrap().unwrap();
+
+        // The caller drops the response future before it completes, e.g.
+        // because the client disconnected. This must still release the
+        // connection's in-flight count, not leak it forever.
+        drop(call);
+        pool.drain_completions();
+        assert!(
+            pool.in_flight.is_empty(),
+            "dropping the response future must release its in_flight slot"
+        );
+
+        // Since the in-flight count was released, removing the endpoint
+        // closes its connection immediately rather than deferring to a
+        // graceful drain.
+        pool.update_pool(Update::DoesNotExist);
+        assert_eq!(metrics.connections.get(), 0);
+        assert_eq!(metrics.draining.get(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn graceful_drain_deadline() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(_a, ())| {
+                PeakEwma::new(
+                    svc0.clone(),
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(30),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, ())]));
+        h0.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        let _call = pool.call(());
+        let (_req, _respond) = h0.next_request().now_or_never().unwrap().unwrap();
+
+        pool.update_pool(Update::DoesNotExist);
+        assert_eq!(metrics.draining.get(), 1);
+
+        // Even though the request never completes, the connection is
+        // forcibly evicted once `drain_timeout` elapses.
+        time::advance(time::Duration::from_secs(30)).await;
+        let ctx = &mut Context::from_waker(futures_util::task::noop_waker_ref());
+        assert_ready_ok!(pool.poll_pool(ctx));
+        assert_eq!(metrics.connections.get(), 0);
+        assert_eq!(metrics.draining.get(), 0);
+    }
+
+    #[tokio::test(flavor = "current_thread", start_paused = true)]
+    async fn draining_connection_id_not_reused() {
+        let _trace = linkerd_tracing::test::with_default_filter("trace");
+
+        let addr0 = "192.168.10.10:80".parse().unwrap();
+        let (svc0, mut h0) = tower_test::mock::pair::<(), ()>();
+        let (svc1, mut h1) = tower_test::mock::pair::<(), ()>();
+
+        let metrics = P2cMetrics::default();
+        let mut pool = P2cPool::new(
+            metrics.clone(),
+            |(_a, n): (SocketAddr, u8)| {
+                PeakEwma::new(
+                    if n == 0 { svc0.clone() } else { svc1.clone() },
+                    time::Duration::from_secs(1),
+                    1.0 * 1000.0 * 1000.0,
+                    CompleteOnResponse::default(),
+                )
+            },
+            OutlierDetectorConfig::default(),
+            2,
+            ConnectionScalingConfig::default(),
+            time::Duration::from_secs(30),
+        );
+
+        pool.update_pool(Update::Reset(vec![(addr0, 0u8)]));
+        h0.allow(1);
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond0) = h0.next_request().now_or_never().unwrap().unwrap();
+
+        // The target at `addr0` changes while the request above is still
+        // outstanding; the old connection should drain rather than being
+        // evicted out from under the in-flight request, and the new
+        // connection it's replaced with must not reuse its connection id.
+        pool.update_pool(Update::Add(vec![(addr0, 1u8)]));
+        assert_eq!(metrics.connections.get(), 2, "old connection still draining");
+        assert_eq!(metrics.draining.get(), 1);
+        assert_eq!(pool.pool.ready_len() + pool.pool.pending_len(), 2);
+
+        // Finishing the old request drains only the old connection, leaving
+        // the new one (and its distinct id) untouched.
+        respond0.send_response(());
+        call.now_or_never()
+            .expect("call should be satisfied")
+            .expect("call should succeed");
+
+        h1.allow(1);
+        let ctx = &mut Context::from_waker(futures_util::task::noop_waker_ref());
+        assert_ready_ok!(pool.poll_pool(ctx));
+        assert_eq!(metrics.connections.get(), 1, "only the new connection remains");
+        assert_eq!(metrics.draining.get(), 0);
+        assert_eq!(pool.pool.ready_len() + pool.pool.pending_len(), 1);
+
+        // The new connection is still live and usable.
+        assert!(pool.ready().now_or_never().is_some());
+        let call = pool.call(());
+        let ((), respond1) = h1.next_request().now_or_never().unwrap().unwrap();
+        respond1.send_response(());
+        call.now_or_never()
+            .expect("call should be satisfied")
+            .expect("call should succeed");
+    }
 }